@@ -0,0 +1,79 @@
+//! Walks the `webassembly/testsuite` submodule at build time and emits one
+//! `#[test]` per `.wast` file, so `cargo test <file_name>` can isolate a
+//! single failing spec file instead of running the whole suite as one
+//! aggregate result.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Files that are known not to pass yet. Listing them here lets the
+/// generated tests assert *something* about them (that they still have at
+/// least one failure) instead of silently skipping them, so the day they start
+/// passing for real shows up as a test failure demanding this list be
+/// trimmed.
+const EXPECTED_FAIL: &[&str] = &[];
+
+fn sanitize(stem: &str) -> String {
+    stem.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn main() {
+    let testsuite_dir = Path::new("tests/testsuite");
+    println!("cargo:rerun-if-changed={}", testsuite_dir.display());
+
+    let mut wast_files: Vec<_> = fs::read_dir(testsuite_dir)
+        .expect("the webassembly/testsuite submodule is checked out at tests/testsuite")
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "wast"))
+        .collect();
+    wast_files.sort();
+
+    if wast_files.is_empty() {
+        panic!(
+            "no .wast files found under {}; the webassembly/testsuite submodule looks \
+             uninitialized -- run `git submodule update --init` before building",
+            testsuite_dir.display(),
+        );
+    }
+
+    let mut generated = String::new();
+    for path in wast_files {
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .expect("wast file has a utf8 name")
+            .to_owned();
+        let test_name = sanitize(
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .expect("wast file has a utf8 stem"),
+        );
+        let expect_fail = EXPECTED_FAIL.contains(&file_name.as_str());
+
+        generated.push_str(&format!("#[test]\nfn {}() {{\n", test_name));
+        generated.push_str(&format!(
+            "    let result = lucet_spectest::run_spec_test(&std::path::PathBuf::from(r#\"{}\"#))\n        .expect(\"{} ran to completion\");\n",
+            path.display(),
+            file_name,
+        ));
+        if expect_fail {
+            generated.push_str(&format!(
+                "    assert!(result.failures() > 0, \"{} is listed in build.rs's EXPECTED_FAIL but now passes -- remove it from the list\");\n",
+                file_name,
+            ));
+        } else {
+            generated.push_str(&format!(
+                "    assert_eq!(result.failures(), 0, \"{} has unexpected failures\");\n",
+                file_name,
+            ));
+        }
+        generated.push_str("}\n\n");
+    }
+
+    let out_dir = env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    let dest = Path::new(&out_dir).join("spec_tests.rs");
+    fs::write(dest, generated).expect("write generated spec tests");
+}
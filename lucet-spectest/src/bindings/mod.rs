@@ -0,0 +1,3 @@
+mod spectest;
+
+pub use self::spectest::spectest_instance;
@@ -0,0 +1,112 @@
+//! The `spectest` host module that the official WebAssembly testsuite expects
+//! every script environment to provide: a handful of print functions that
+//! just echo their arguments to stdout,
+//! a table, a memory, and four globals, all under the module name
+//! `spectest`.
+use lucet_runtime::vmctx::Vmctx;
+use lucet_runtime::Bindings;
+use lucet_runtime_macros::lucet_hostcall;
+use std::collections::HashMap;
+
+/// The `spectest` module itself, expressed in `.wat` so that it can be
+/// instantiated through the same `lucetc` pipeline as any other script
+/// module. The `print*` exports simply forward to host-provided functions;
+/// the globals, table, and memory are the exact shapes the testsuite
+/// hardcodes.
+const SPECTEST_WAT: &str = r#"
+(module
+  (import "spectest_host" "print" (func $host_print))
+  (import "spectest_host" "print_i32" (func $host_print_i32 (param i32)))
+  (import "spectest_host" "print_i64" (func $host_print_i64 (param i64)))
+  (import "spectest_host" "print_f32" (func $host_print_f32 (param f32)))
+  (import "spectest_host" "print_f64" (func $host_print_f64 (param f64)))
+  (import "spectest_host" "print_i32_f32" (func $host_print_i32_f32 (param i32 f32)))
+  (import "spectest_host" "print_f64_f64" (func $host_print_f64_f64 (param f64 f64)))
+
+  (func (export "print") (call $host_print))
+  (func (export "print_i32") (param i32) (call $host_print_i32 (local.get 0)))
+  (func (export "print_i64") (param i64) (call $host_print_i64 (local.get 0)))
+  (func (export "print_f32") (param f32) (call $host_print_f32 (local.get 0)))
+  (func (export "print_f64") (param f64) (call $host_print_f64 (local.get 0)))
+  (func (export "print_i32_f32") (param i32 f32)
+    (call $host_print_i32_f32 (local.get 0) (local.get 1)))
+  (func (export "print_f64_f64") (param f64 f64)
+    (call $host_print_f64_f64 (local.get 0) (local.get 1)))
+
+  (global (export "global_i32") i32 (i32.const 666))
+  (global (export "global_i64") i64 (i64.const 666))
+  (global (export "global_f32") f32 (f32.const 666.0))
+  (global (export "global_f64") f64 (f64.const 666.0))
+
+  (table (export "table") 10 20 funcref)
+  (memory (export "memory") 1 2))
+"#;
+
+#[lucet_hostcall]
+#[no_mangle]
+pub extern "C" fn spectest_host_print(_vmctx: &Vmctx) {
+    println!("spectest.print()");
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub extern "C" fn spectest_host_print_i32(_vmctx: &Vmctx, val: i32) {
+    println!("spectest.print_i32({})", val);
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub extern "C" fn spectest_host_print_i64(_vmctx: &Vmctx, val: i64) {
+    println!("spectest.print_i64({})", val);
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub extern "C" fn spectest_host_print_f32(_vmctx: &Vmctx, val: f32) {
+    println!("spectest.print_f32({})", val);
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub extern "C" fn spectest_host_print_f64(_vmctx: &Vmctx, val: f64) {
+    println!("spectest.print_f64({})", val);
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub extern "C" fn spectest_host_print_i32_f32(_vmctx: &Vmctx, i: i32, f: f32) {
+    println!("spectest.print_i32_f32({}, {})", i, f);
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub extern "C" fn spectest_host_print_f64_f64(_vmctx: &Vmctx, a: f64, b: f64) {
+    println!("spectest.print_f64_f64({}, {})", a, b);
+}
+
+fn spectest_bindings() -> Bindings {
+    let mut symbols = HashMap::new();
+    symbols.insert("print".to_owned(), "spectest_host_print".to_owned());
+    symbols.insert("print_i32".to_owned(), "spectest_host_print_i32".to_owned());
+    symbols.insert("print_i64".to_owned(), "spectest_host_print_i64".to_owned());
+    symbols.insert("print_f32".to_owned(), "spectest_host_print_f32".to_owned());
+    symbols.insert("print_f64".to_owned(), "spectest_host_print_f64".to_owned());
+    symbols.insert(
+        "print_i32_f32".to_owned(),
+        "spectest_host_print_i32_f32".to_owned(),
+    );
+    symbols.insert(
+        "print_f64_f64".to_owned(),
+        "spectest_host_print_f64_f64".to_owned(),
+    );
+    let mut module_symbols = HashMap::new();
+    module_symbols.insert("spectest_host".to_owned(), symbols);
+    Bindings::env(module_symbols)
+}
+
+/// The wasm bytes and host bindings for the `spectest` module, ready to be
+/// instantiated and registered under the name `spectest` by `ScriptEnv::new`.
+pub fn spectest_instance() -> (Vec<u8>, Bindings) {
+    let wasm = wabt::wat2wasm(SPECTEST_WAT).expect("spectest module is valid wat");
+    (wasm, spectest_bindings())
+}
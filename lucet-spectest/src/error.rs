@@ -0,0 +1,57 @@
+use failure::{Backtrace, Context, Fail};
+use std::fmt::{self, Display};
+
+#[derive(Debug)]
+pub struct SpecTestError {
+    inner: Context<SpecTestErrorKind>,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Fail)]
+pub enum SpecTestErrorKind {
+    #[fail(display = "unsupported command")]
+    UnsupportedCommand,
+    #[fail(display = "unsupported by lucetc")]
+    UnsupportedLucetc,
+    #[fail(display = "unexpected failure")]
+    UnexpectedFailure,
+    #[fail(display = "unexpected success")]
+    UnexpectedSuccess,
+    #[fail(display = "incorrect result")]
+    IncorrectResult,
+}
+
+impl SpecTestError {
+    pub fn get_context(&self) -> &SpecTestErrorKind {
+        self.inner.get_context()
+    }
+}
+
+impl Fail for SpecTestError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.inner.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.inner.backtrace()
+    }
+}
+
+impl Display for SpecTestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
+
+impl From<SpecTestErrorKind> for SpecTestError {
+    fn from(kind: SpecTestErrorKind) -> SpecTestError {
+        SpecTestError {
+            inner: Context::new(kind),
+        }
+    }
+}
+
+impl From<Context<SpecTestErrorKind>> for SpecTestError {
+    fn from(inner: Context<SpecTestErrorKind>) -> SpecTestError {
+        SpecTestError { inner }
+    }
+}
@@ -0,0 +1,83 @@
+use lucet_runtime::{Bindings, InstanceHandle, UntypedRetVal, Val};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ValueType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+#[derive(Clone, Debug)]
+pub enum ExportType {
+    Function(Vec<ValueType>, Vec<ValueType>),
+    Global(ValueType),
+    Memory,
+    Table,
+}
+
+/// A loaded module, paired with the export types the script runner cares
+/// about so that `AssertReturn`-style commands can tell functions from
+/// globals without re-inspecting the module.
+pub struct LucetInstance {
+    handle: InstanceHandle,
+    export_types: HashMap<String, ExportType>,
+}
+
+impl LucetInstance {
+    pub fn new(handle: InstanceHandle, export_types: HashMap<String, ExportType>) -> Self {
+        Self {
+            handle,
+            export_types,
+        }
+    }
+
+    /// Reads the export types straight off the wasm bytes a module was
+    /// instantiated from.
+    pub fn export_types(wasm: &[u8]) -> HashMap<String, ExportType> {
+        crate::wasm_meta::export_types(wasm)
+    }
+
+    pub fn type_of(&self, field: &str) -> Option<ExportType> {
+        self.export_types.get(field).cloned()
+    }
+
+    /// The current value of an exported global, or `None` if `field` isn't
+    /// a global export.
+    pub fn global_value(&self, field: &str) -> Option<UntypedRetVal> {
+        match self.export_types.get(field) {
+            Some(ExportType::Global(_)) => Some(self.handle.global_value(field)),
+            _ => None,
+        }
+    }
+
+    /// Invokes `field` and returns every value it produced. Returns a `Vec`
+    /// so that the caller doesn't need to special-case single- vs.
+    /// multi-value results, but `lucet_runtime`'s ABI only ever hands back
+    /// one raw value per call, so the `Vec` is empty when the callee
+    /// declares no results and holds that one value otherwise (including
+    /// for a callee that declares several results, or whose type is not
+    /// known): a void-returning export comes back as an empty `Vec`, not a
+    /// 1-element one holding a meaningless raw return register.
+    pub fn run(&mut self, field: &str, args: &[Val]) -> Result<Vec<UntypedRetVal>, lucet_runtime::Error> {
+        let nresults = match self.export_types.get(field) {
+            Some(ExportType::Function(_, ref rets)) => rets.len(),
+            _ => 1,
+        };
+        let retval = self.handle.run(field, args)?;
+        Ok(if nresults == 0 { Vec::new() } else { vec![retval] })
+    }
+
+    /// Symbol bindings another module's imports can resolve against when
+    /// this instance has been `Register`ed under `as_name`.
+    pub fn export_bindings(&self, as_name: &str) -> Bindings {
+        let mut symbols = HashMap::new();
+        for field in self.export_types.keys() {
+            symbols.insert(field.clone(), field.clone());
+        }
+        let mut module_symbols = HashMap::new();
+        module_symbols.insert(as_name.to_owned(), symbols);
+        Bindings::env(module_symbols)
+    }
+}
@@ -0,0 +1,127 @@
+use crate::error::SpecTestError;
+use serde::Serialize;
+use serde_json::{json, Value};
+use wabt::script::{Command, CommandKind};
+
+enum Outcome {
+    Pass,
+    Skip(SpecTestError),
+    Fail(SpecTestError),
+}
+
+/// One command's outcome, shaped for serialization rather than for the
+/// runner's own bookkeeping.
+#[derive(Serialize)]
+struct CommandReport {
+    description: String,
+    line: u64,
+    outcome: &'static str,
+    error_kind: Option<String>,
+    error: Option<String>,
+}
+
+struct CommandResult {
+    description: String,
+    line: u64,
+    outcome: Outcome,
+}
+
+/// The accumulated pass/skip/fail outcome of every command in a single
+/// `.wast` script.
+pub struct SpecScriptResult {
+    results: Vec<CommandResult>,
+}
+
+impl SpecScriptResult {
+    pub fn new() -> Self {
+        SpecScriptResult {
+            results: Vec::new(),
+        }
+    }
+
+    pub fn pass(&mut self, cmd: &Command) {
+        self.results.push(CommandResult {
+            description: command_description(&cmd.kind),
+            line: cmd.line,
+            outcome: Outcome::Pass,
+        });
+    }
+
+    pub fn skip(&mut self, cmd: &Command, e: SpecTestError) {
+        self.results.push(CommandResult {
+            description: command_description(&cmd.kind),
+            line: cmd.line,
+            outcome: Outcome::Skip(e),
+        });
+    }
+
+    pub fn fail(&mut self, cmd: &Command, e: SpecTestError) {
+        self.results.push(CommandResult {
+            description: command_description(&cmd.kind),
+            line: cmd.line,
+            outcome: Outcome::Fail(e),
+        });
+    }
+
+    pub fn failures(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.outcome, Outcome::Fail(_)))
+            .count()
+    }
+
+    /// A structured, per-command breakdown of this result: kind/description,
+    /// source line, outcome, and on failure or skip the `SpecTestErrorKind`
+    /// and error message. Intended for feeding CI or dashboards that diff
+    /// results across lucet revisions.
+    pub fn to_json(&self) -> Value {
+        let commands: Vec<CommandReport> = self
+            .results
+            .iter()
+            .map(|r| {
+                let (outcome, error_kind, error) = match &r.outcome {
+                    Outcome::Pass => ("pass", None, None),
+                    Outcome::Skip(e) => (
+                        "skip",
+                        Some(format!("{:?}", e.get_context())),
+                        Some(e.to_string()),
+                    ),
+                    Outcome::Fail(e) => (
+                        "fail",
+                        Some(format!("{:?}", e.get_context())),
+                        Some(e.to_string()),
+                    ),
+                };
+                CommandReport {
+                    description: r.description.clone(),
+                    line: r.line,
+                    outcome,
+                    error_kind,
+                    error,
+                }
+            })
+            .collect();
+        json!({ "commands": commands })
+    }
+}
+
+pub fn command_description(kind: &CommandKind) -> String {
+    match kind {
+        CommandKind::Module { name, .. } => format!("module {:?}", name),
+        CommandKind::AssertInvalid { .. } => "assert_invalid".to_owned(),
+        CommandKind::AssertMalformed { .. } => "assert_malformed".to_owned(),
+        CommandKind::AssertUninstantiable { .. } => "assert_uninstantiable".to_owned(),
+        CommandKind::AssertUnlinkable { .. } => "assert_unlinkable".to_owned(),
+        CommandKind::Register { as_name, .. } => format!("register {}", as_name),
+        CommandKind::PerformAction(action) => format!("action {:?}", action),
+        CommandKind::AssertReturn { action, .. } => format!("assert_return {:?}", action),
+        CommandKind::AssertReturnCanonicalNan { action } => {
+            format!("assert_return_canonical_nan {:?}", action)
+        }
+        CommandKind::AssertReturnArithmeticNan { action } => {
+            format!("assert_return_arithmetic_nan {:?}", action)
+        }
+        CommandKind::AssertTrap { action, .. } => format!("assert_trap {:?}", action),
+        CommandKind::AssertExhaustion { action } => format!("assert_exhaustion {:?}", action),
+    }
+}
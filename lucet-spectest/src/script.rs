@@ -0,0 +1,143 @@
+use crate::bindings::spectest_instance;
+use crate::instance::LucetInstance;
+use failure::Fail;
+use lucet_runtime::{Bindings, DlModule, Error as RuntimeError, Region, UntypedRetVal, Val};
+use std::collections::HashMap;
+
+#[derive(Debug, Fail)]
+pub enum ScriptError {
+    #[fail(display = "validation error: {}", _0)]
+    ValidationError(String),
+    #[fail(display = "deserialize error: {}", _0)]
+    DeserializeError(String),
+    #[fail(display = "compile error: {}", _0)]
+    CompileError(String),
+    #[fail(display = "runtime error: {}", _0)]
+    RuntimeError(#[fail(cause)] RuntimeError),
+    #[fail(display = "no such instance")]
+    NoSuchInstance,
+    #[fail(display = "no such global export: {}", _0)]
+    NoSuchGlobal(String),
+}
+
+impl From<RuntimeError> for ScriptError {
+    fn from(e: RuntimeError) -> ScriptError {
+        ScriptError::RuntimeError(e)
+    }
+}
+
+/// Tracks every module a `.wast` script has instantiated so far, plus the
+/// names it has registered for other modules to import from, mirroring the
+/// way the reference interpreter keeps a running "store".
+pub struct ScriptEnv {
+    instances: Vec<(Option<String>, LucetInstance)>,
+    registered: HashMap<String, usize>,
+}
+
+impl ScriptEnv {
+    pub fn new() -> Self {
+        let mut env = ScriptEnv {
+            instances: Vec::new(),
+            registered: HashMap::new(),
+        };
+
+        let (spectest_wasm, spectest_bindings) = spectest_instance();
+        let spectest_name = Some("spectest".to_owned());
+        env.instantiate_with_bindings(spectest_wasm, &spectest_name, spectest_bindings)
+            .expect("the built-in spectest module always instantiates");
+        env.register(&spectest_name, &"spectest".to_owned())
+            .expect("the built-in spectest module always registers");
+
+        env
+    }
+
+    pub fn instantiate(
+        &mut self,
+        module: Vec<u8>,
+        name: &Option<String>,
+    ) -> Result<(), ScriptError> {
+        self.instantiate_with_bindings(module, name, Bindings::empty())
+    }
+
+    fn instantiate_with_bindings(
+        &mut self,
+        module: Vec<u8>,
+        name: &Option<String>,
+        mut bindings: Bindings,
+    ) -> Result<(), ScriptError> {
+        for (registered_name, idx) in self.registered.iter() {
+            bindings.extend(self.instances[*idx].1.export_bindings(registered_name));
+        }
+
+        let dl_module = DlModule::load_from_bytes(&module, &bindings)
+            .map_err(|e| ScriptError::CompileError(e.to_string()))?;
+        let region = lucet_runtime::MmapRegion::create(1, &lucet_runtime::Limits::default())
+            .map_err(|e| ScriptError::CompileError(e.to_string()))?;
+        let handle = region
+            .new_instance(dl_module)
+            .map_err(|e| ScriptError::RuntimeError(e))?;
+
+        let export_types = LucetInstance::export_types(&module);
+        self.instances
+            .push((name.clone(), LucetInstance::new(handle, export_types)));
+        Ok(())
+    }
+
+    pub fn delete_last(&mut self) {
+        self.instances.pop();
+    }
+
+    pub fn register(&mut self, name: &Option<String>, as_name: &String) -> Result<(), ScriptError> {
+        let idx = self.lookup(name).ok_or(ScriptError::NoSuchInstance)?;
+        self.registered.insert(as_name.clone(), idx);
+        Ok(())
+    }
+
+    fn lookup(&self, name: &Option<String>) -> Option<usize> {
+        match name {
+            Some(name) => self
+                .instances
+                .iter()
+                .rposition(|(n, _)| n.as_ref() == Some(name))
+                .or_else(|| self.registered.get(name).copied()),
+            None => {
+                if self.instances.is_empty() {
+                    None
+                } else {
+                    Some(self.instances.len() - 1)
+                }
+            }
+        }
+    }
+
+    pub fn instance_named(&self, name: &Option<String>) -> Option<&LucetInstance> {
+        self.lookup(name).map(|idx| &self.instances[idx].1)
+    }
+
+    fn instance_named_mut(&mut self, name: &Option<String>) -> Option<&mut LucetInstance> {
+        self.lookup(name).map(move |idx| &mut self.instances[idx].1)
+    }
+
+    pub fn run(
+        &mut self,
+        module: &Option<String>,
+        field: &str,
+        args: Vec<Val>,
+    ) -> Result<Vec<UntypedRetVal>, ScriptError> {
+        let instance = self
+            .instance_named_mut(module)
+            .ok_or(ScriptError::NoSuchInstance)?;
+        instance.run(field, &args).map_err(ScriptError::from)
+    }
+
+    pub fn get_global(
+        &self,
+        module: &Option<String>,
+        field: &str,
+    ) -> Result<UntypedRetVal, ScriptError> {
+        self.instance_named(module)
+            .ok_or(ScriptError::NoSuchInstance)?
+            .global_value(field)
+            .ok_or_else(|| ScriptError::NoSuchGlobal(field.to_owned()))
+    }
+}
@@ -0,0 +1,304 @@
+//! A minimal walk of the core wasm binary format, just far enough to answer
+//! "what does this module export, and at what type". `lucet_runtime`'s
+//! compiled module doesn't hand back export signatures, so we read them
+//! straight off the bytes the script handed to `instantiate`, the same
+//! bytes that get passed to `lucetc`.
+use crate::instance::{ExportType, ValueType};
+use std::collections::HashMap;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        let b = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn leb_u32(&mut self) -> Option<u32> {
+        let mut result: u32 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            result |= ((byte & 0x7f) as u32).checked_shl(shift).unwrap_or(0);
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        Some(result)
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let len = self.leb_u32()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    /// Skips a constant init expr (a single instruction followed by `end`),
+    /// which is all core wasm allows in a global's initializer.
+    fn skip_init_expr(&mut self) {
+        match self.u8() {
+            Some(0x41) | Some(0x42) => {
+                self.leb_u32();
+            }
+            Some(0x43) => {
+                self.bytes(4);
+            }
+            Some(0x44) => {
+                self.bytes(8);
+            }
+            Some(0x23) => {
+                self.leb_u32();
+            }
+            _ => {}
+        }
+        // consume the `end` opcode (0x0b)
+        self.u8();
+    }
+
+    fn valtype(&mut self) -> Option<ValueType> {
+        match self.u8()? {
+            0x7f => Some(ValueType::I32),
+            0x7e => Some(ValueType::I64),
+            0x7d => Some(ValueType::F32),
+            0x7c => Some(ValueType::F64),
+            // reference and v128 types are not classified; `export_types`
+            // treats this `None` as unparseable and reports no exports at
+            // all for the module.
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct FuncType {
+    params: Vec<ValueType>,
+    results: Vec<ValueType>,
+}
+
+/// Parses the export section of a wasm module, resolving each exported
+/// function back to its declared parameter and result types via the
+/// module's type/import/function sections.
+pub fn export_types(wasm: &[u8]) -> HashMap<String, ExportType> {
+    let mut exports = HashMap::new();
+    let mut r = Reader::new(wasm);
+
+    if r.bytes(8).is_none() {
+        // not even a full wasm header; nothing to export
+        return exports;
+    }
+
+    let mut types: Vec<FuncType> = Vec::new();
+    let mut func_type_indices: Vec<u32> = Vec::new();
+    let mut global_types: Vec<ValueType> = Vec::new();
+
+    while let Some(section_id) = r.u8() {
+        let section_len = match r.leb_u32() {
+            Some(l) => l as usize,
+            None => break,
+        };
+        let section_end = r.pos + section_len;
+
+        // Sections 1/2/3/6 build the type/function/global index spaces that
+        // the export section (7) resolves against. A value type this reader
+        // can't classify (a reftype, v128, ...) means an index in one of
+        // those spaces would silently desync from here on, so any such
+        // section bails out of the whole parse instead of pushing a
+        // mis-indexed entry -- better to report no exports than wrong ones.
+        let section_ok = match section_id {
+            // type section
+            1 => (|| -> Option<()> {
+                let count = r.leb_u32()?;
+                for _ in 0..count {
+                    if r.u8()? != 0x60 {
+                        return None;
+                    }
+                    let nparams = r.leb_u32()?;
+                    let mut params = Vec::with_capacity(nparams as usize);
+                    for _ in 0..nparams {
+                        params.push(r.valtype()?);
+                    }
+                    let nresults = r.leb_u32()?;
+                    let mut results = Vec::with_capacity(nresults as usize);
+                    for _ in 0..nresults {
+                        results.push(r.valtype()?);
+                    }
+                    types.push(FuncType { params, results });
+                }
+                Some(())
+            })(),
+            // import section: imported funcs/globals occupy the front of
+            // their index spaces, ahead of anything declared locally.
+            2 => (|| -> Option<()> {
+                let count = r.leb_u32()?;
+                for _ in 0..count {
+                    r.name()?;
+                    r.name()?;
+                    match r.u8()? {
+                        0x00 => func_type_indices.push(r.leb_u32()?),
+                        0x01 => {
+                            r.u8()?; // elem type
+                            let flags = r.u8()?;
+                            r.leb_u32()?;
+                            if flags & 0x01 != 0 {
+                                r.leb_u32()?;
+                            }
+                        }
+                        0x02 => {
+                            let flags = r.u8()?;
+                            r.leb_u32()?;
+                            if flags & 0x01 != 0 {
+                                r.leb_u32()?;
+                            }
+                        }
+                        0x03 => {
+                            global_types.push(r.valtype()?);
+                            r.u8()?; // mutability
+                        }
+                        _ => return None,
+                    }
+                }
+                Some(())
+            })(),
+            // function section: type indices for locally-defined functions
+            3 => (|| -> Option<()> {
+                let count = r.leb_u32()?;
+                for _ in 0..count {
+                    func_type_indices.push(r.leb_u32()?);
+                }
+                Some(())
+            })(),
+            // global section: locally-defined globals
+            6 => (|| -> Option<()> {
+                let count = r.leb_u32()?;
+                for _ in 0..count {
+                    global_types.push(r.valtype()?);
+                    r.u8()?; // mutability
+                    r.skip_init_expr();
+                }
+                Some(())
+            })(),
+            _ => Some(()),
+        };
+        if section_ok.is_none() {
+            return exports;
+        }
+
+        match section_id {
+            // export section
+            7 => {
+                if let Some(count) = r.leb_u32() {
+                    for _ in 0..count {
+                        let name = match r.name() {
+                            Some(n) => n,
+                            None => break,
+                        };
+                        let kind = r.u8();
+                        let index = r.leb_u32().unwrap_or(0) as usize;
+                        let export = match kind {
+                            Some(0x00) => func_type_indices
+                                .get(index)
+                                .and_then(|typeidx| types.get(*typeidx as usize))
+                                .map(|ty| {
+                                    ExportType::Function(ty.params.clone(), ty.results.clone())
+                                }),
+                            Some(0x01) => Some(ExportType::Table),
+                            Some(0x02) => Some(ExportType::Memory),
+                            Some(0x03) => {
+                                global_types.get(index).map(|vt| ExportType::Global(*vt))
+                            }
+                            _ => None,
+                        };
+                        if let Some(export) = export {
+                            exports.insert(name, export);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        r.pos = section_end;
+    }
+
+    exports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WASM_HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+    /// A module with a single imported i32 global, re-exported under the
+    /// same name, encoded by hand straight from the binary format spec.
+    fn module_with_imported_global() -> Vec<u8> {
+        let mut wasm = WASM_HEADER.to_vec();
+        // import section: one global import "m"."g" : i32, immutable
+        wasm.extend_from_slice(&[
+            2, 8, // section id, length
+            1, // count
+            1, b'm', // module name
+            1, b'g', // field name
+            0x03, 0x7f, 0x00, // kind=global, valtype=i32, mutability=const
+        ]);
+        // export section: re-export global index 0 as "g"
+        wasm.extend_from_slice(&[
+            7, 5, // section id, length
+            1, // count
+            1, b'g', // export name
+            0x03, 0x00, // kind=global, index=0
+        ]);
+        wasm
+    }
+
+    #[test]
+    fn imported_global_is_exported_at_the_right_index() {
+        let exports = export_types(&module_with_imported_global());
+        match exports.get("g") {
+            Some(ExportType::Global(ValueType::I32)) => {}
+            other => panic!("expected Global(I32), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unrecognized_import_valtype_aborts_instead_of_desyncing_indices() {
+        let mut wasm = WASM_HEADER.to_vec();
+        // import section: one *unrecognized* global (funcref, 0x70), which
+        // this reader can't classify -- followed by what would be a second,
+        // well-formed i32 global import if indices stayed in sync.
+        wasm.extend_from_slice(&[
+            2, 15, // section id, length
+            2, // count
+            1, b'm', 1, b'a', 0x03, 0x70, 0x00, // "m"."a": funcref global
+            1, b'm', 1, b'b', 0x03, 0x7f, 0x00, // "m"."b": i32 global
+        ]);
+        // export section: re-export global index 1, which would resolve to
+        // "b" if the reader had silently skipped "a" and kept counting.
+        wasm.extend_from_slice(&[
+            7, 5, // section id, length
+            1, // count
+            1, b'b', // export name
+            0x03, 0x01, // kind=global, index=1
+        ]);
+
+        // The unrecognized valtype must abort the whole parse rather than
+        // hand back a "b" -> Global(I32) export resolved against a
+        // desynced index.
+        assert!(export_types(&wasm).is_empty());
+    }
+}
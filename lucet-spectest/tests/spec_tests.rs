@@ -0,0 +1,3 @@
+//! One `#[test]` per `.wast` file in the `webassembly/testsuite` submodule,
+//! generated by `build.rs`.
+include!(concat!(env!("OUT_DIR"), "/spec_tests.rs"));